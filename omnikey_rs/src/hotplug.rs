@@ -0,0 +1,71 @@
+//! Hotplug notifications for the OMNIKEY reader.
+//!
+//! Where `rusb`/libusb support hotplug on the host platform, lets
+//! callers react to the physical device being plugged in or removed
+//! as distinct lifecycle events, instead of only finding out the next
+//! time they happen to poll.
+
+use std::time::Duration;
+
+use rusb::{Hotplug, UsbContext};
+
+use crate::structs::{OMNIKEY_PRODUCT_ID, OMNIKEY_VENDOR_ID};
+
+/// A hotplug event for the OMNIKEY device specifically; other devices
+/// are filtered out by the underlying `libusb` hotplug filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotplugEvent {
+    Arrived,
+    Left,
+}
+
+struct Callback<F: FnMut(HotplugEvent) + Send + 'static>(F);
+
+impl<F: FnMut(HotplugEvent) + Send + 'static> Hotplug<rusb::GlobalContext> for Callback<F> {
+    fn device_arrived(&mut self, _device: rusb::Device<rusb::GlobalContext>) {
+        (self.0)(HotplugEvent::Arrived);
+    }
+
+    fn device_left(&mut self, _device: rusb::Device<rusb::GlobalContext>) {
+        (self.0)(HotplugEvent::Left);
+    }
+}
+
+/// Registers `on_event` to be called whenever the OMNIKEY is plugged
+/// in or unplugged. Returns the registration handle; dropping it
+/// unregisters the callback.
+///
+/// # Returns
+/// A result where:
+/// - On `Ok()`, returns the registration handle
+/// - On `Err()`, returns a String detailing the error, including when
+///   [`rusb::has_hotplug`] reports the platform doesn't support it
+pub fn register<F>(on_event: F) -> Result<rusb::Registration<rusb::GlobalContext>, String>
+where
+    F: FnMut(HotplugEvent) + Send + 'static,
+{
+    if !rusb::has_hotplug() {
+        return Err("libusb hotplug support is not available on this platform".to_string());
+    }
+
+    rusb::HotplugBuilder::new()
+        .vendor_id(OMNIKEY_VENDOR_ID)
+        .product_id(OMNIKEY_PRODUCT_ID)
+        .enumerate(true)
+        .register(rusb::GlobalContext {}, Box::new(Callback(on_event)))
+        .map_err(|e| format!("Error registering hotplug callback: {}", e))
+}
+
+/// Pumps the `libusb` event loop once so registered hotplug callbacks
+/// actually get invoked. Callers drive this from their own polling
+/// loop (e.g. on a blocking thread) with a short timeout.
+///
+/// # Returns
+/// A result where:
+/// - On `Ok()`, returns nothing
+/// - On `Err()`, returns a String detailing the error
+pub fn pump_events(timeout: Duration) -> Result<(), String> {
+    rusb::GlobalContext {}
+        .handle_events(Some(timeout))
+        .map_err(|e| format!("Error handling USB events: {}", e))
+}
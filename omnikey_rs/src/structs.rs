@@ -12,9 +12,9 @@ use rusb::{
 };
 
 /// The USB Vendor ID for the OMNIKEY 5025CL
-const OMNIKEY_VENDOR_ID: u16 = 0x076B;
+pub(crate) const OMNIKEY_VENDOR_ID: u16 = 0x076B;
 /// The USB Product ID for the OMNIKEY 5025CL
-const OMNIKEY_PRODUCT_ID: u16 = 0x502A;
+pub(crate) const OMNIKEY_PRODUCT_ID: u16 = 0x502A;
 
 /// A struct representing the physical OMNIKEY
 /// reader.
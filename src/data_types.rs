@@ -0,0 +1,57 @@
+//! Shared data types used across the websocket, database,
+//! and reader layers: configuration loaded at startup and
+//! the rows/DTOs that move between MySQL and connected
+//! clients.
+
+use serde::{Deserialize, Serialize};
+
+/// Top level application configuration, loaded once at
+/// startup (see the `config` crate setup in `main`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Settings {
+    pub login: LoginSettings,
+    /// Outbound MQTT bridge config; absent (or the whole `mqtt`
+    /// feature disabled) means the bridge is never started.
+    #[cfg(feature = "mqtt")]
+    pub mqtt: Option<MqttSettings>,
+}
+
+/// Credentials used to connect to the shop MySQL database.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginSettings {
+    pub user: String,
+    pub pass: String,
+    pub database: String,
+}
+
+/// Connection details for the optional outbound MQTT bridge.
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Clone, Deserialize)]
+pub struct MqttSettings {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Topic prefix events are published under, e.g.
+    /// `motorsports/shop`.
+    pub base_topic: String,
+}
+
+/// Row shape returned by the `people` / `in_shop` join query.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct JoinedPersonInShopSQL {
+    pub rcsid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub rfid: String,
+    pub time_in: chrono::DateTime<chrono::Utc>,
+}
+
+/// A person currently checked in to the shop, shaped for
+/// serialization out to websocket clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinedPersonInShop {
+    pub rcsid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub timestamp: String,
+}
@@ -0,0 +1,94 @@
+//! Prometheus metrics for the shop service.
+//!
+//! Tracks connected websocket clients, messages received by
+//! `msgtype`, RFID scan outcomes, live shop occupancy, and MySQL
+//! query latency. Scraped over `GET /metrics`, meant to be combined
+//! with the existing websocket route: `ws_route.or(metrics::route())`.
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use warp::Filter;
+
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub struct Metrics {
+    pub connected_users: IntGauge,
+    pub messages_total: IntCounterVec,
+    pub rfid_scans_total: IntCounterVec,
+    pub occupancy: IntGauge,
+    pub db_query_duration: Histogram,
+    registry: Registry,
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        let registry = Registry::new();
+
+        let connected_users = IntGauge::new(
+            "sids_connected_users",
+            "Currently connected websocket clients",
+        )
+        .unwrap();
+        let messages_total = IntCounterVec::new(
+            Opts::new("sids_messages_total", "Messages received, by msgtype"),
+            &["msgtype"],
+        )
+        .unwrap();
+        let rfid_scans_total = IntCounterVec::new(
+            Opts::new("sids_rfid_scans_total", "RFID scans, by validity"),
+            &["valid"],
+        )
+        .unwrap();
+        let occupancy = IntGauge::new(
+            "sids_occupancy",
+            "People currently checked in to the shop",
+        )
+        .unwrap();
+        let db_query_duration = Histogram::with_opts(HistogramOpts::new(
+            "sids_db_query_duration_seconds",
+            "MySQL query duration in seconds",
+        ))
+        .unwrap();
+
+        registry
+            .register(Box::new(connected_users.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(messages_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(rfid_scans_total.clone()))
+            .unwrap();
+        registry.register(Box::new(occupancy.clone())).unwrap();
+        registry
+            .register(Box::new(db_query_duration.clone()))
+            .unwrap();
+
+        Metrics {
+            connected_users,
+            messages_total,
+            rfid_scans_total,
+            occupancy,
+            db_query_duration,
+            registry,
+        }
+    }
+
+    fn render(&self) -> String {
+        let metric_families = self.registry.gather();
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&metric_families, &mut buf)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+/// The `GET /metrics` warp route.
+pub fn route() -> impl Filter<Extract = (String,), Error = std::convert::Infallible> + Clone {
+    warp::path("metrics")
+        .and(warp::get())
+        .map(|| METRICS.render())
+}
@@ -0,0 +1,143 @@
+//! Optional outbound MQTT bridge.
+//!
+//! Publishes the same check-in/check-out events the RFID polling loop
+//! already produces to a configurable broker, plus periodic retained
+//! occupancy snapshots, so other tooling (building automation, Slack
+//! notifiers, attendance logging) can consume shop presence without
+//! touching MySQL or opening a websocket. Entirely feature-gated:
+//! deployments without a broker configured never pull in an MQTT
+//! client.
+
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use serde::Serialize;
+
+use crate::data_types::Settings;
+use crate::store::{PresenceChange, Store};
+
+/// A connected outbound MQTT bridge.
+#[derive(Debug, Clone)]
+pub struct Bridge {
+    client: AsyncClient,
+    base_topic: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PresenceEvent<'a> {
+    rcsid: &'a str,
+    name: String,
+    timestamp: String,
+    direction: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct OccupancySnapshot {
+    count: i64,
+    timestamp: String,
+}
+
+impl Bridge {
+    /// Connects to the broker configured in `settings.mqtt`, spawning
+    /// the background event loop task `rumqttc` requires. Returns
+    /// `None` (and starts nothing) if no broker is configured.
+    pub fn connect(settings: &Settings) -> Option<Bridge> {
+        let config = settings.mqtt.as_ref()?;
+
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+
+        let (client, mut event_loop) = AsyncClient::new(options, 10);
+
+        tokio::task::spawn(async move {
+            loop {
+                if let Err(e) = event_loop.poll().await {
+                    error!("MQTT event loop error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        });
+
+        Some(Bridge {
+            client,
+            base_topic: config.base_topic.clone(),
+        })
+    }
+
+    /// Publishes a check-in/check-out event for `rfid` to
+    /// `{base_topic}/{rfid}/presence`, mirroring the `PresenceChange`
+    /// the RFID polling loop just applied.
+    pub async fn publish_presence(&self, rfid: &str, change: &PresenceChange) {
+        let (rcsid, name, direction) = match change {
+            PresenceChange::CheckedIn(person) => (
+                person.rcsid.as_str(),
+                format!("{} {}", person.firstname, person.lastname),
+                "in",
+            ),
+            PresenceChange::CheckedOut {
+                rcsid,
+                firstname,
+                lastname,
+            } => (
+                rcsid.as_str(),
+                format!("{} {}", firstname, lastname),
+                "out",
+            ),
+            PresenceChange::UnknownCard => return,
+        };
+
+        let event = PresenceEvent {
+            rcsid,
+            name,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            direction,
+        };
+
+        let topic = format!("{}/{}/presence", self.base_topic, rfid);
+        self.publish(&topic, &event, false).await;
+    }
+
+    /// Publishes a retained occupancy snapshot to `{base_topic}/occupancy`
+    /// so late subscribers get the current count immediately.
+    pub async fn publish_occupancy(&self, count: i64) {
+        let snapshot = OccupancySnapshot {
+            count,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let topic = format!("{}/occupancy", self.base_topic);
+        self.publish(&topic, &snapshot, true).await;
+    }
+
+    async fn publish<T: Serialize>(&self, topic: &str, payload: &T, retain: bool) {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize MQTT payload for {}: {}", topic, e);
+                return;
+            }
+        };
+
+        if let Err(e) = self
+            .client
+            .publish(topic, QoS::AtLeastOnce, retain, body)
+            .await
+        {
+            error!("Failed to publish to {}: {}", topic, e);
+        }
+    }
+}
+
+/// Spawns a task that publishes an occupancy snapshot to `bridge`
+/// every `interval`, independent of the RFID polling loop.
+pub fn spawn_occupancy_snapshots(bridge: Bridge, store: Store, interval: Duration) {
+    tokio::task::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match store.count_in_shop().await {
+                Ok(count) => bridge.publish_occupancy(count).await,
+                Err(e) => error!("Failed to read occupancy for MQTT snapshot: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,151 @@
+//! Resilient lifecycle management for the physical OMNIKEY reader.
+//!
+//! `Reader::new()` only scans the device list once and fails
+//! permanently if the OMNIKEY isn't present, and nothing recovers if
+//! it's unplugged mid-operation. `ReaderSupervisor` retries
+//! connecting with backoff while the device is missing, reacts
+//! immediately to hotplug arrival where the platform supports it, and
+//! broadcasts a `reader_status` message so the dashboard shows when
+//! the scanner goes offline.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use omnikey_rs::hotplug;
+use omnikey_rs::structs::Reader;
+use tokio::sync::{Notify, RwLock};
+
+use crate::websocket::{broadcast_authenticated, OutgoingMessage, Users};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Owns the current `Reader` handle, if connected, and broadcasts
+/// `reader_status` to `users` whenever that changes.
+pub struct ReaderSupervisor {
+    reader: RwLock<Option<Arc<Reader>>>,
+    users: Users,
+}
+
+impl ReaderSupervisor {
+    pub fn new(users: Users) -> Arc<ReaderSupervisor> {
+        Arc::new(ReaderSupervisor {
+            reader: RwLock::new(None),
+            users,
+        })
+    }
+
+    /// Returns the currently connected reader, if any.
+    pub async fn reader(&self) -> Option<Arc<Reader>> {
+        self.reader.read().await.clone()
+    }
+
+    /// Marks the reader as gone after a USB I/O error, so the
+    /// supervision loop reconnects instead of repeating the same
+    /// failure against a dead handle.
+    pub async fn mark_failed(&self) {
+        let mut guard = self.reader.write().await;
+        if guard.take().is_some() {
+            error!("OMNIKEY reader disconnected.");
+            self.broadcast_status(false).await;
+        }
+    }
+
+    /// Runs forever, keeping `reader` populated whenever the device is
+    /// reachable.
+    pub async fn run(self: Arc<Self>, config: RetryConfig) {
+        let notify = Arc::new(Notify::new());
+        let registration = {
+            let notify = notify.clone();
+            hotplug::register(move |event| {
+                if event == hotplug::HotplugEvent::Arrived {
+                    notify.notify_one();
+                }
+            })
+        };
+
+        // Where hotplug isn't supported we just fall back to the
+        // backoff retry loop below.
+        let _registration = match registration {
+            Ok(r) => Some(r),
+            Err(e) => {
+                info!(
+                    "Hotplug unavailable, falling back to polling retry only: {}",
+                    e
+                );
+                None
+            }
+        };
+
+        if _registration.is_some() {
+            // libusb only fires hotplug callbacks while its event loop is
+            // being pumped, so drive that on its own blocking thread.
+            tokio::task::spawn_blocking(|| loop {
+                if hotplug::pump_events(Duration::from_millis(250)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        let mut backoff = config.initial_backoff;
+
+        loop {
+            if self.reader().await.is_some() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            // `connect()` is a handful of synchronous libusb calls
+            // (device enumeration plus two 100ms-timeout bulk
+            // transfers); offload it like `rfid::poll_loop` does for
+            // `check_for_rfid_card` so a reconnect attempt never blocks
+            // a tokio worker thread.
+            let connected = tokio::task::spawn_blocking(connect).await;
+
+            match connected {
+                Ok(Ok(reader)) => {
+                    info!("OMNIKEY reader connected.");
+                    *self.reader.write().await = Some(Arc::new(reader));
+                    backoff = config.initial_backoff;
+                    self.broadcast_status(true).await;
+                }
+                Ok(Err(e)) => {
+                    error!("Reader unavailable, retrying in {:?}: {}", backoff, e);
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = notify.notified() => {}
+                    }
+                    backoff = (backoff * 2).min(config.max_backoff);
+                }
+                Err(e) => {
+                    error!("Reader connect task panicked: {}", e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(config.max_backoff);
+                }
+            }
+        }
+    }
+
+    async fn broadcast_status(&self, connected: bool) {
+        let message = OutgoingMessage::ReaderStatus { connected };
+        broadcast_authenticated(&self.users, &message).await;
+    }
+}
+
+fn connect() -> Result<Reader, String> {
+    let reader = Reader::new()?;
+    reader.set_legacy_ccid_mode()?;
+    Ok(reader)
+}
@@ -0,0 +1,188 @@
+//! Background RFID polling daemon.
+//!
+//! Bridges the synchronous [`omnikey_rs::structs::Reader`] to the rest of the
+//! shop system: polls the physical reader on an interval, debounces
+//! repeated reads of the same card, and turns each fresh scan into a
+//! shop check-in/check-out that gets broadcast to connected websocket
+//! clients.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::reader_supervisor::ReaderSupervisor;
+use crate::store::{PresenceChange, Store};
+use crate::websocket::{broadcast_authenticated, OutgoingMessage, Users};
+
+#[cfg(feature = "mqtt")]
+use crate::mqtt::Bridge;
+#[cfg(not(feature = "mqtt"))]
+type Bridge = ();
+
+/// How often the reader is polled for a card, and how long a given
+/// card id is ignored for after being seen, to absorb the repeated
+/// reads a card produces while held near the antenna.
+#[derive(Debug, Clone, Copy)]
+pub struct PollConfig {
+    pub poll_interval: Duration,
+    pub debounce_window: Duration,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        PollConfig {
+            poll_interval: Duration::from_millis(200),
+            debounce_window: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Lifecycle handle for the polling loop, mirroring the pause/resume/
+/// shutdown shape of a BLE central's scan state so the rest of the
+/// system can control scanning without tearing down the task.
+#[derive(Debug, Default)]
+pub struct ScanState {
+    paused: AtomicBool,
+    stopped: AtomicBool,
+}
+
+impl ScanState {
+    pub fn new() -> Arc<ScanState> {
+        Arc::new(ScanState::default())
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+/// Runs forever (until `state` is stopped), polling the reader held
+/// by `supervisor` for RFID scans and driving shop check-in/check-out
+/// off of valid, debounced reads. While the reader is disconnected,
+/// polling is simply skipped until `supervisor` reconnects it.
+pub async fn poll_loop(
+    supervisor: Arc<ReaderSupervisor>,
+    state: Arc<ScanState>,
+    users: Users,
+    store: Store,
+    bridge: Option<Bridge>,
+    config: PollConfig,
+) {
+    let mut last_seen: HashMap<u64, Instant> = HashMap::new();
+
+    loop {
+        if state.is_stopped() {
+            info!("RFID poll loop stopped.");
+            return;
+        }
+
+        tokio::time::sleep(config.poll_interval).await;
+
+        if state.is_paused() {
+            continue;
+        }
+
+        let reader = match supervisor.reader().await {
+            Some(reader) => reader,
+            None => continue,
+        };
+        let scan = tokio::task::spawn_blocking(move || reader.check_for_rfid_card()).await;
+
+        let data = match scan {
+            Ok(Ok(data)) => data,
+            Ok(Err(e)) => {
+                error!("Error reading RFID card: {}", e);
+                supervisor.mark_failed().await;
+                continue;
+            }
+            Err(e) => {
+                error!("RFID poll task panicked: {}", e);
+                continue;
+            }
+        };
+
+        let validity = if data.valid { "true" } else { "false" };
+        crate::metrics::METRICS
+            .rfid_scans_total
+            .with_label_values(&[validity])
+            .inc();
+
+        if !data.valid {
+            continue;
+        }
+
+        let now = Instant::now();
+        if let Some(seen_at) = last_seen.get(&data.id) {
+            if now.duration_since(*seen_at) < config.debounce_window {
+                continue;
+            }
+        }
+        last_seen.insert(data.id, now);
+
+        if let Err(e) = handle_scan(data.id, &users, &store, bridge.as_ref()).await {
+            error!("Failed to process RFID scan {:010x}: {}", data.id, e);
+        }
+    }
+}
+
+/// Looks up the scanned card, toggles its presence in `in_shop`, and
+/// broadcasts the resulting check-in/check-out message to both
+/// websocket clients and the MQTT bridge, if one is configured.
+async fn handle_scan(
+    id: u64,
+    users: &Users,
+    store: &Store,
+    bridge: Option<&Bridge>,
+) -> Result<(), sqlx::Error> {
+    let rfid = format!("{:010x}", id);
+    let change = store.toggle_presence(&rfid).await?;
+
+    publish_to_mqtt(bridge, &rfid, &change).await;
+
+    let message = match change {
+        PresenceChange::CheckedIn(person) => {
+            info!("{} checked in", person.rcsid);
+            OutgoingMessage::InShopAdd {
+                people: vec![person],
+            }
+        }
+        PresenceChange::CheckedOut { rcsid, .. } => {
+            info!("{} checked out", rcsid);
+            OutgoingMessage::InShopRemove { rcsid }
+        }
+        PresenceChange::UnknownCard => {
+            info!("Scanned unknown card {}", rfid);
+            return Ok(());
+        }
+    };
+
+    broadcast_authenticated(users, &message).await;
+    Ok(())
+}
+
+#[cfg(feature = "mqtt")]
+async fn publish_to_mqtt(bridge: Option<&Bridge>, rfid: &str, change: &PresenceChange) {
+    if let Some(bridge) = bridge {
+        bridge.publish_presence(rfid, change).await;
+    }
+}
+
+#[cfg(not(feature = "mqtt"))]
+async fn publish_to_mqtt(_bridge: Option<&Bridge>, _rfid: &str, _change: &PresenceChange) {}
@@ -0,0 +1,200 @@
+//! Pooled, transactional access to the shop MySQL database.
+//!
+//! Replaces the old pattern of opening a fresh `MySqlConnectOptions`
+//! connection (and `.unwrap()`-ing it) on every incoming message with
+//! a `sqlx::MySqlPool` created once at startup. `Store` wraps that
+//! pool and is cheap to `Clone` (the pool itself is reference
+//! counted), so it can be handed to every handler that needs the
+//! database.
+
+use std::collections::HashSet;
+
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+use sqlx::MySqlPool;
+
+use crate::auth::{Identity, Scope};
+use crate::data_types::*;
+use crate::metrics::METRICS;
+
+/// Pooled handle to the shop database.
+#[derive(Debug, Clone)]
+pub struct Store {
+    pool: MySqlPool,
+}
+
+/// The outcome of toggling a card's presence in `in_shop`.
+#[derive(Debug, Clone)]
+pub enum PresenceChange {
+    CheckedIn(JoinedPersonInShop),
+    CheckedOut {
+        rcsid: String,
+        firstname: String,
+        lastname: String,
+    },
+    /// The card doesn't match anyone in `people`.
+    UnknownCard,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct PersonSQL {
+    rcsid: String,
+    firstname: String,
+    lastname: String,
+}
+
+impl Store {
+    /// Connects a pool to the database described by `settings`. Should
+    /// be called once at startup; the returned `Store` is cloned into
+    /// every handler that needs it.
+    pub async fn connect(settings: &Settings) -> Result<Store, sqlx::Error> {
+        let opts = MySqlConnectOptions::new()
+            .host("localhost")
+            .username(&settings.login.user)
+            .password(&settings.login.pass)
+            .database(&settings.login.database);
+        let pool = MySqlPoolOptions::new().connect_with(opts).await?;
+        Ok(Store { pool })
+    }
+
+    /// Lists everyone currently checked in to the shop.
+    pub async fn list_people_in_shop(&self) -> Result<Vec<JoinedPersonInShop>, sqlx::Error> {
+        let _timer = METRICS.db_query_duration.start_timer();
+        let rows = sqlx::query_as::<_, JoinedPersonInShopSQL>(concat!(
+            "select people.rcsid, people.firstname, people.lastname, people.rfid, in_shop.time_in ",
+            "from people ",
+            "inner join in_shop on in_shop.rfid=people.rfid"
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|obj| JoinedPersonInShop {
+                rcsid: obj.rcsid,
+                firstname: obj.firstname,
+                lastname: obj.lastname,
+                timestamp: format!("{} {}", obj.time_in.date_naive(), obj.time_in.time()),
+            })
+            .collect())
+    }
+
+    /// Toggles `rfid`'s presence in `in_shop` as a single committed
+    /// transaction, so a scan never leaves the table half-updated:
+    /// begin -> read current presence -> insert or delete -> commit.
+    pub async fn toggle_presence(&self, rfid: &str) -> Result<PresenceChange, sqlx::Error> {
+        let _timer = METRICS.db_query_duration.start_timer();
+        let mut tx = self.pool.begin().await?;
+
+        let person = sqlx::query_as::<_, PersonSQL>(
+            "select rcsid, firstname, lastname from people where rfid = ?",
+        )
+        .bind(rfid)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let person = match person {
+            Some(person) => person,
+            None => {
+                tx.rollback().await?;
+                return Ok(PresenceChange::UnknownCard);
+            }
+        };
+
+        let currently_in_shop = sqlx::query_as::<_, (String,)>(
+            "select rfid from in_shop where rfid = ? for update",
+        )
+        .bind(rfid)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+        let change = if currently_in_shop {
+            sqlx::query("delete from in_shop where rfid = ?")
+                .bind(rfid)
+                .execute(&mut *tx)
+                .await?;
+
+            PresenceChange::CheckedOut {
+                rcsid: person.rcsid,
+                firstname: person.firstname,
+                lastname: person.lastname,
+            }
+        } else {
+            sqlx::query("insert into in_shop (rfid, time_in) values (?, now())")
+                .bind(rfid)
+                .execute(&mut *tx)
+                .await?;
+
+            PresenceChange::CheckedIn(JoinedPersonInShop {
+                rcsid: person.rcsid,
+                firstname: person.firstname,
+                lastname: person.lastname,
+                timestamp: chrono::Utc::now().naive_utc().to_string(),
+            })
+        };
+
+        tx.commit().await?;
+
+        match &change {
+            PresenceChange::CheckedIn(_) => METRICS.occupancy.inc(),
+            PresenceChange::CheckedOut { .. } => METRICS.occupancy.dec(),
+            PresenceChange::UnknownCard => {}
+        }
+
+        Ok(change)
+    }
+
+    /// Counts everyone currently checked in, used to initialize the
+    /// `sids_occupancy` gauge at startup.
+    pub async fn count_in_shop(&self) -> Result<i64, sqlx::Error> {
+        let _timer = METRICS.db_query_duration.start_timer();
+        let (count,): (i64,) = sqlx::query_as("select count(*) from in_shop")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// Checks a username/password pair against the `credentials`
+    /// table and returns the resulting `Identity` if they match.
+    pub async fn verify_credentials(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<Identity>, sqlx::Error> {
+        let _timer = METRICS.db_query_duration.start_timer();
+        let row = sqlx::query_as::<_, CredentialSQL>(
+            "select user_id, display_name, password_hash, is_admin from credentials where username = ?",
+        )
+        .bind(username)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        if !bcrypt::verify(password, &row.password_hash).unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let mut scopes = HashSet::from([Scope::Member]);
+        if row.is_admin {
+            scopes.insert(Scope::Admin);
+        }
+
+        Ok(Some(Identity {
+            user_id: row.user_id,
+            display_name: row.display_name,
+            scopes,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct CredentialSQL {
+    user_id: i64,
+    display_name: String,
+    password_hash: String,
+    is_admin: bool,
+}
@@ -0,0 +1,189 @@
+use crate::auth::{Identity, TokenStore};
+use crate::store::Store;
+
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use futures_util::{SinkExt, TryFutureExt, StreamExt};
+use tokio::sync::{mpsc, RwLock};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use warp::ws::{Message, WebSocket};
+
+mod protocol;
+
+pub use protocol::{dispatch, IncomingMessage, OutgoingMessage, ProtocolError};
+
+/// Our global unique user id counter.
+pub static NEXT_USER_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// A connected socket: its outgoing channel, plus whatever identity
+/// it has authenticated as (`None` until a successful login/resume).
+pub struct Session {
+    pub tx: mpsc::UnboundedSender<Message>,
+    pub identity: Option<Identity>,
+}
+
+/// Our state of currently connected users.
+///
+/// - Key is their id
+/// - Value is their `Session`
+pub type Users = Arc<RwLock<HashMap<usize, Session>>>;
+
+pub async fn user_connected(ws: WebSocket, users: Users, store: Store, tokens: TokenStore) {
+    // Use a counter to assign a new unique ID for this user.
+    let my_id = NEXT_USER_ID.fetch_add(1, Ordering::Relaxed);
+
+    info!("User connected: {}", my_id);
+    crate::metrics::METRICS.connected_users.inc();
+
+    // Split the socket into a sender and receive of messages.
+    let (mut user_ws_tx, mut user_ws_rx) = ws.split();
+
+    // Use an unbounded channel to handle buffering and flushing of messages
+    // to the websocket...
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut rx = UnboundedReceiverStream::new(rx);
+
+    tokio::task::spawn(async move {
+        while let Some(message) = rx.next().await {
+            user_ws_tx
+                .send(message)
+                .unwrap_or_else(|e| {
+                    error!("Websocket failed to send: {}", e);
+                })
+                .await;
+        }
+    });
+
+    // Save the session in our list of connected users. Nobody is
+    // authenticated yet.
+    users.write().await.insert(
+        my_id,
+        Session {
+            tx,
+            identity: None,
+        },
+    );
+
+    // Return a `Future` that is basically a state machine managing
+    // this specific user's connection.
+
+    // Every time the user sends a message, dispatch it; an
+    // unauthenticated socket that sends anything but a login/resume
+    // gets disconnected.
+    while let Some(result) = user_ws_rx.next().await {
+        let msg = match result {
+            Ok(msg) => msg,
+            Err(e) => {
+                error!("websocket error(uid={}): {}", my_id, e);
+                break;
+            }
+        };
+
+        if !user_message(my_id, msg, &users, &store, &tokens).await {
+            break;
+        }
+    }
+
+    // user_ws_rx stream will keep processing as long as the user stays
+    // connected. Once they disconnect, then...
+    user_disconnected(my_id, &users).await;
+}
+
+/// Handles one incoming message for `my_id`. Returns `false` if the
+/// connection should be closed (an unauthenticated socket sent
+/// something other than a login/resume, including a message that
+/// doesn't parse).
+pub async fn user_message(
+    my_id: usize,
+    msg: Message,
+    users: &Users,
+    store: &Store,
+    tokens: &TokenStore,
+) -> bool {
+    // Skip any non-Text messages...
+    let msg = if let Ok(s) = msg.to_str() {
+        log_received(my_id, s);
+        s
+    } else {
+        return true;
+    };
+
+    let identity = users
+        .read()
+        .await
+        .get(&my_id)
+        .and_then(|session| session.identity.clone());
+    let was_authenticated = identity.is_some();
+
+    let (reply, keep_open) = match dispatch(msg, store, tokens, identity.as_ref()).await {
+        Ok((reply, Some(new_identity))) => {
+            if let Some(session) = users.write().await.get_mut(&my_id) {
+                session.identity = Some(new_identity);
+            }
+            (reply, true)
+        }
+        Ok((reply, None)) => (reply, true),
+        Err(e) => {
+            error!("Failed to dispatch message (uid={}): {}", my_id, e);
+            // An unauthenticated socket only ever gets to send a
+            // login/resume; any failure here (unrecognized message,
+            // garbage JSON, a non-login message type, ...) means it's
+            // done something other than that, so disconnect it. A
+            // rejected login or resume is not a failure here: it comes
+            // back as `LoginFailed` and the socket stays open.
+            (OutgoingMessage::from(e), was_authenticated)
+        }
+    };
+
+    reply_to(users, my_id, &reply).await;
+    keep_open
+}
+
+/// Logs an incoming message body, except for `login`/`resume`, whose
+/// `response`/`token` fields hold a plaintext password and a bearer
+/// token respectively — those are logged by `msgtype` only.
+fn log_received(my_id: usize, raw: &str) {
+    let msgtype = serde_json::from_str::<serde_json::Value>(raw)
+        .ok()
+        .and_then(|v| v.get("msgtype")?.as_str().map(str::to_string));
+
+    match msgtype.as_deref() {
+        Some(t @ ("login" | "resume")) => {
+            info!("Message received (uid={}): msgtype={} [redacted]", my_id, t);
+        }
+        _ => info!("Message received (uid={}): {}", my_id, raw),
+    }
+}
+
+async fn reply_to(users: &Users, my_id: usize, reply: &OutgoingMessage) {
+    let text = serde_json::to_string(reply).unwrap();
+    if let Some(session) = users.read().await.get(&my_id) {
+        let _ = session.tx.send(Message::text(text));
+    }
+}
+
+/// Sends `message` to every *authenticated* connected session. Used
+/// for presence/status pushes that aren't a direct reply to a
+/// request, so an unauthenticated socket never receives shop data
+/// just by staying connected.
+pub async fn broadcast_authenticated(users: &Users, message: &OutgoingMessage) {
+    let text = serde_json::to_string(message).unwrap();
+    for session in users.read().await.values() {
+        if session.identity.is_some() {
+            let _ = session.tx.send(Message::text(text.clone()));
+        }
+    }
+}
+
+pub async fn user_disconnected(my_id: usize, users: &Users) {
+    info!("User {} left.", my_id);
+    crate::metrics::METRICS.connected_users.dec();
+
+    // Stream closed up, so remove from the user list
+    users.write().await.remove(&my_id);
+}
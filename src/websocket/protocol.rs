@@ -0,0 +1,204 @@
+//! Typed, tagged websocket protocol.
+//!
+//! Replaces the old string-matching `user_message` body with a
+//! statically exhaustive `IncomingMessage` -> `OutgoingMessage`
+//! dispatcher. Both enums are serialized with an internally tagged
+//! representation so the wire format stays `{"msgtype": "...", ...}`
+//! while the Rust side gets exhaustiveness checking and `match`.
+
+use std::fmt;
+
+use crate::auth::{self, Identity, Scope, TokenStore};
+use crate::data_types::JoinedPersonInShop;
+use crate::store::Store;
+
+/// A message sent by a connected client.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "msgtype")]
+pub enum IncomingMessage {
+    /// First message on a new connection: either a fresh SASL-style
+    /// login, or resuming a session with a previously issued token.
+    #[serde(rename = "login")]
+    Login { mechanism: String, response: String },
+    #[serde(rename = "resume")]
+    Resume { token: String },
+    /// Liveness check; answered with `OutgoingMessage::Pong`.
+    #[serde(rename = "ping")]
+    Ping,
+    /// Request the full list of people currently checked in to the shop.
+    #[serde(rename = "get_all_people")]
+    GetAllPeople,
+    /// Subscribe to one or more broadcast topics.
+    #[serde(rename = "subscribe")]
+    Subscribe { topics: Vec<String> },
+    /// A privileged command, gated behind the `Admin` scope.
+    #[serde(rename = "admin_command")]
+    AdminCommand { command: String },
+}
+
+impl IncomingMessage {
+    /// The wire `msgtype`, used as the label for the
+    /// `sids_messages_total` metric.
+    fn kind(&self) -> &'static str {
+        match self {
+            IncomingMessage::Login { .. } => "login",
+            IncomingMessage::Resume { .. } => "resume",
+            IncomingMessage::Ping => "ping",
+            IncomingMessage::GetAllPeople => "get_all_people",
+            IncomingMessage::Subscribe { .. } => "subscribe",
+            IncomingMessage::AdminCommand { .. } => "admin_command",
+        }
+    }
+}
+
+/// A message sent back to a connected client.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "msgtype")]
+pub enum OutgoingMessage {
+    #[serde(rename = "login_ok")]
+    LoginOk { token: String, display_name: String },
+    #[serde(rename = "login_failed")]
+    LoginFailed { reason: String },
+    #[serde(rename = "pong")]
+    Pong,
+    #[serde(rename = "in_shop_add")]
+    InShopAdd { people: Vec<JoinedPersonInShop> },
+    #[serde(rename = "in_shop_remove")]
+    InShopRemove { rcsid: String },
+    #[serde(rename = "subscribed")]
+    Subscribed { topics: Vec<String> },
+    #[serde(rename = "admin_ack")]
+    AdminAck { command: String },
+    /// Broadcast whenever the physical OMNIKEY reader connects or
+    /// disconnects; see `reader_supervisor`.
+    #[serde(rename = "reader_status")]
+    ReaderStatus { connected: bool },
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+/// Everything that can go wrong while dispatching an `IncomingMessage`.
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// The message body didn't parse as any known `IncomingMessage` variant.
+    UnknownMessage(serde_json::Error),
+    /// The database returned an error while handling the request.
+    Database(sqlx::Error),
+    /// A non-login message arrived on an unauthenticated socket.
+    NotAuthenticated,
+    /// The session's scopes don't permit the requested operation.
+    Forbidden,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::UnknownMessage(e) => write!(f, "unrecognized message: {}", e),
+            ProtocolError::Database(e) => write!(f, "database error: {}", e),
+            ProtocolError::NotAuthenticated => write!(f, "not authenticated"),
+            ProtocolError::Forbidden => write!(f, "insufficient scope for this command"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<ProtocolError> for OutgoingMessage {
+    fn from(err: ProtocolError) -> Self {
+        OutgoingMessage::Error {
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Parses a raw text message and dispatches it to the appropriate
+/// handler, returning the reply to send back to the originating
+/// client and, if the message just authenticated the socket, the
+/// `Identity` that should be attached to its session from now on.
+///
+/// Unknown message types never panic or produce a silent `"null"`
+/// reply; they come back as a structured `OutgoingMessage::Error`.
+/// Every variant other than `Login`/`Resume` requires `identity` to
+/// already be `Some`.
+pub async fn dispatch(
+    raw: &str,
+    store: &Store,
+    tokens: &TokenStore,
+    identity: Option<&Identity>,
+) -> Result<(OutgoingMessage, Option<Identity>), ProtocolError> {
+    let incoming: IncomingMessage =
+        serde_json::from_str(raw).map_err(ProtocolError::UnknownMessage)?;
+
+    crate::metrics::METRICS
+        .messages_total
+        .with_label_values(&[incoming.kind()])
+        .inc();
+
+    match incoming {
+        IncomingMessage::Login { mechanism, response } => {
+            match auth::login(store, tokens, &mechanism, &response).await {
+                Ok((token, identity)) => {
+                    let reply = OutgoingMessage::LoginOk {
+                        token,
+                        display_name: identity.display_name.clone(),
+                    };
+                    Ok((reply, Some(identity)))
+                }
+                Err(e) => Ok((
+                    OutgoingMessage::LoginFailed {
+                        reason: e.to_string(),
+                    },
+                    None,
+                )),
+            }
+        }
+        IncomingMessage::Resume { token } => match tokens.resume(&token).await {
+            Some(identity) => {
+                let reply = OutgoingMessage::LoginOk {
+                    token,
+                    display_name: identity.display_name.clone(),
+                };
+                Ok((reply, Some(identity)))
+            }
+            None => Ok((
+                OutgoingMessage::LoginFailed {
+                    reason: "unknown or expired token".to_string(),
+                },
+                None,
+            )),
+        },
+        other => {
+            let identity = identity.ok_or(ProtocolError::NotAuthenticated)?;
+            let reply = dispatch_authenticated(other, store, identity).await?;
+            Ok((reply, None))
+        }
+    }
+}
+
+async fn dispatch_authenticated(
+    incoming: IncomingMessage,
+    store: &Store,
+    identity: &Identity,
+) -> Result<OutgoingMessage, ProtocolError> {
+    match incoming {
+        IncomingMessage::Login { .. } | IncomingMessage::Resume { .. } => unreachable!(),
+        IncomingMessage::Ping => Ok(OutgoingMessage::Pong),
+        IncomingMessage::GetAllPeople => get_all_people(store).await,
+        IncomingMessage::Subscribe { topics } => Ok(OutgoingMessage::Subscribed { topics }),
+        IncomingMessage::AdminCommand { command } => {
+            if !identity.has_scope(Scope::Admin) {
+                return Err(ProtocolError::Forbidden);
+            }
+            Ok(OutgoingMessage::AdminAck { command })
+        }
+    }
+}
+
+async fn get_all_people(store: &Store) -> Result<OutgoingMessage, ProtocolError> {
+    let people = store
+        .list_people_in_shop()
+        .await
+        .map_err(ProtocolError::Database)?;
+
+    Ok(OutgoingMessage::InShopAdd { people })
+}